@@ -1,4 +1,4 @@
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::TcpListener;
 use futures::{SinkExt, StreamExt};
 use std::str::FromStr;
 use std::{net, process};
@@ -8,10 +8,246 @@ use ldap3_server::LdapCodec;
 use ldap3_server::simple::{ServerOps, LdapResultCode, WhoamiRequest, LdapSearchScope,
                             DisconnectionNotice, SimpleBindRequest, LdapMsg, LdapFilter,
                             LdapPartialAttribute, SearchRequest, LdapSearchResultEntry};
+use ldap3_server::proto::{LdapOp, LdapExtendedRequest, LdapExtendedResponse,
+                           LdapPasswordModifyRequest, LdapResult};
 use sqlite::{Connection, OpenFlags, Value};
 use regex::Regex;
 use crypto::sha2::Sha512;
 use crypto::digest::Digest;
+use rand::RngCore;
+use serde::Deserialize;
+use tokio_rustls::{TlsAcceptor, rustls};
+
+// RFC 3062 "Password Modify" extended operation OID.
+const OID_PASSWORD_MODIFY: &str = "1.3.6.1.4.1.4203.1.11.1";
+// RFC 4532 "Who am I?" extended operation OID, decoded by ServerOps itself.
+const OID_WHOAMI: &str = "1.3.6.1.4.1.4203.1.11.3";
+
+// Path to the TOML config file, overridable so containers can mount it
+// wherever is convenient; the individual settings it carries can then be
+// overridden again by the `SIMPLELDAP_*` environment variables below.
+const ENV_CONFIG_PATH: &str = "SIMPLELDAP_CONFIG";
+const ENV_LISTEN_ADDR: &str = "SIMPLELDAP_LISTEN_ADDR";
+const ENV_DB_PATH: &str = "SIMPLELDAP_DB_PATH";
+const ENV_BASE_DN: &str = "SIMPLELDAP_BASE_DN";
+const ENV_LDAPS_LISTEN_ADDR: &str = "SIMPLELDAP_LDAPS_LISTEN_ADDR";
+const ENV_TLS_CERT_PATH: &str = "SIMPLELDAP_TLS_CERT_PATH";
+const ENV_TLS_KEY_PATH: &str = "SIMPLELDAP_TLS_KEY_PATH";
+
+fn default_listen_addr() -> String { "0.0.0.0:12345".to_string() }
+fn default_db_path() -> String { "database.sqlite".to_string() }
+fn default_base_dn() -> String { "dc=example,dc=com".to_string() }
+fn default_ldaps_listen_addr() -> String { "0.0.0.0:636".to_string() }
+fn default_tls_path() -> String { "".to_string() }
+
+// Server configuration: a TOML file (`simpleldap.toml` by default, or
+// whatever `SIMPLELDAP_CONFIG` points at) with every field optional,
+// further overridden field-by-field by `SIMPLELDAP_*` environment
+// variables so the same file can be reused across instances.
+#[derive(Deserialize)]
+struct Config {
+    #[serde(default = "default_listen_addr")]
+    listen_addr: String,
+    #[serde(default = "default_db_path")]
+    db_path: String,
+    #[serde(default = "default_base_dn")]
+    base_dn: String,
+    // LDAPS is only started once both `tls_cert_path` and `tls_key_path`
+    // are non-empty; otherwise the server runs plaintext-only as before.
+    #[serde(default = "default_ldaps_listen_addr")]
+    ldaps_listen_addr: String,
+    #[serde(default = "default_tls_path")]
+    tls_cert_path: String,
+    #[serde(default = "default_tls_path")]
+    tls_key_path: String,
+}
+
+impl Config {
+    fn load() -> Config {
+        let config_path = std::env::var(ENV_CONFIG_PATH).unwrap_or_else(|_| "simpleldap.toml".to_string());
+        let mut config: Config = match std::fs::read_to_string(&config_path) {
+            Ok(contents) => match toml::from_str(contents.as_str()) {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("Unable to parse config file {}: {}", config_path, e);
+                    process::exit(-1);
+                }
+            },
+            Err(_) => match toml::from_str("") {
+                Ok(c) => c,
+                Err(e) => {
+                    eprintln!("Unable to build default config: {}", e);
+                    process::exit(-1);
+                }
+            },
+        };
+        if let Ok(v) = std::env::var(ENV_LISTEN_ADDR) {
+            config.listen_addr = v;
+        }
+        if let Ok(v) = std::env::var(ENV_DB_PATH) {
+            config.db_path = v;
+        }
+        if let Ok(v) = std::env::var(ENV_BASE_DN) {
+            config.base_dn = v;
+        }
+        if let Ok(v) = std::env::var(ENV_LDAPS_LISTEN_ADDR) {
+            config.ldaps_listen_addr = v;
+        }
+        if let Ok(v) = std::env::var(ENV_TLS_CERT_PATH) {
+            config.tls_cert_path = v;
+        }
+        if let Ok(v) = std::env::var(ENV_TLS_KEY_PATH) {
+            config.tls_key_path = v;
+        }
+        // A half-configured TLS setup (e.g. a typo'd env var dropping just
+        // one of the two paths) must not silently fall back to plaintext --
+        // that would defeat the entire point of offering LDAPS.
+        if config.tls_cert_path.is_empty() != config.tls_key_path.is_empty() {
+            eprintln!("Both tls_cert_path and tls_key_path must be set together (or neither)");
+            process::exit(-1);
+        }
+        return config;
+    }
+
+    fn tls_enabled(&self) -> bool {
+        !self.tls_cert_path.is_empty() && !self.tls_key_path.is_empty()
+    }
+}
+
+// Builds a `tokio-rustls` acceptor from a PEM certificate chain and PEM
+// private key on disk, for the dedicated LDAPS listener.
+fn load_tls_acceptor(cert_path: &str, key_path: &str) -> Result<TlsAcceptor, String> {
+    let cert_file = std::fs::File::open(cert_path)
+        .map_err(|e| format!("Unable to open TLS certificate {}: {}", cert_path, e))?;
+    let certs = rustls_pemfile::certs(&mut std::io::BufReader::new(cert_file))
+        .map_err(|e| format!("Unable to parse TLS certificate {}: {}", cert_path, e))?
+        .into_iter()
+        .map(rustls::Certificate)
+        .collect();
+
+    let key_file = std::fs::read(key_path)
+        .map_err(|e| format!("Unable to open TLS key {}: {}", key_path, e))?;
+    // Accept both PKCS#8 and the older PKCS#1/RSA PEM format, since the
+    // latter is what `openssl genrsa` and many self-signed cert guides
+    // still produce.
+    let mut keys = rustls_pemfile::pkcs8_private_keys(&mut key_file.as_slice())
+        .map_err(|e| format!("Unable to parse TLS key {}: {}", key_path, e))?;
+    if keys.is_empty() {
+        keys = rustls_pemfile::rsa_private_keys(&mut key_file.as_slice())
+            .map_err(|e| format!("Unable to parse TLS key {}: {}", key_path, e))?;
+    }
+    let key = match keys.pop() {
+        Some(k) => rustls::PrivateKey(k),
+        None => {
+            return Err(format!("No PKCS#8 or PKCS#1 private key found in {}", key_path));
+        }
+    };
+
+    let server_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Unable to build TLS config: {}", e))?;
+
+    return Ok(TlsAcceptor::from(std::sync::Arc::new(server_config)));
+}
+
+// `passhash` scheme prefixes, RFC 2307 style. Anything without one of these
+// is treated as a bare, unsalted SHA-512 hex digest for backward compatibility
+// with databases created before pluggable hashing existed.
+const SCHEME_SSHA512: &str = "{SSHA512}";
+const SCHEME_SHA512: &str = "{SHA512}";
+const SCHEME_ARGON2: &str = "{ARGON2}";
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut diff: u8 = 0;
+    for (x, y) in a.iter().zip(b.iter()) {
+        diff |= x ^ y;
+    }
+    diff == 0
+}
+
+fn sha512_hex(input: &[u8]) -> String {
+    let mut hasher = Sha512::new();
+    hasher.input(input);
+    hasher.result_str()
+}
+
+// Verifies a clear-text password against a stored `passhash` value,
+// dispatching on its `{SCHEME}` prefix.
+fn verify_password(stored: &str, clear: &str) -> bool {
+    if let Some(rest) = stored.strip_prefix(SCHEME_SSHA512) {
+        let decoded = match base64::decode(rest) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Unable to decode {} hash: {}", SCHEME_SSHA512, e);
+                return false;
+            }
+        };
+        if decoded.len() <= 64 {
+            eprintln!("Malformed {} hash: missing salt", SCHEME_SSHA512);
+            return false;
+        }
+        let (hash, salt) = decoded.split_at(64);
+        let mut salted = Vec::with_capacity(clear.len() + salt.len());
+        salted.extend_from_slice(clear.as_bytes());
+        salted.extend_from_slice(salt);
+        let mut hasher = Sha512::new();
+        hasher.input(&salted);
+        let mut computed = [0u8; 64];
+        hasher.result(&mut computed);
+        return constant_time_eq(hash, &computed);
+    }
+    if let Some(rest) = stored.strip_prefix(SCHEME_ARGON2) {
+        return match argon2::verify_encoded(rest, clear.as_bytes()) {
+            Ok(ok) => ok,
+            Err(e) => {
+                eprintln!("Unable to verify {} hash: {}", SCHEME_ARGON2, e);
+                false
+            }
+        };
+    }
+    let rest = stored.strip_prefix(SCHEME_SHA512).unwrap_or(stored);
+    constant_time_eq(rest.as_bytes(), sha512_hex(clear.as_bytes()).as_bytes())
+}
+
+// Hashes a clear-text password into the scheme this server writes for new or
+// changed passwords: salted SHA-512, stored as `{SSHA512}base64(hash||salt)`.
+fn hash_password(clear: &str) -> String {
+    let mut salt = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut salt);
+    let mut salted = Vec::with_capacity(clear.len() + salt.len());
+    salted.extend_from_slice(clear.as_bytes());
+    salted.extend_from_slice(&salt);
+    let mut hasher = Sha512::new();
+    hasher.input(&salted);
+    let mut hash = [0u8; 64];
+    hasher.result(&mut hash);
+    let mut combined = Vec::with_capacity(hash.len() + salt.len());
+    combined.extend_from_slice(&hash);
+    combined.extend_from_slice(&salt);
+    format!("{}{}", SCHEME_SSHA512, base64::encode(&combined))
+}
+
+fn gen_extended_response(msgid: i32, code: LdapResultCode, message: String) -> LdapMsg {
+    LdapMsg {
+        msgid,
+        op: LdapOp::ExtendedResponse(LdapExtendedResponse {
+            res: LdapResult {
+                code: code,
+                matcheddn: "".to_string(),
+                message: message,
+                referral: vec![],
+            },
+            name: None,
+            value: None,
+        }),
+        ctrl: vec![],
+    }
+}
 
 #[derive(Clone)]
 struct Database {
@@ -28,6 +264,13 @@ struct UserDef {
     surname: String,
 }
 
+struct GroupDef {
+    dn: String,
+    cn: String,
+    gid_number: i64,
+    members: Vec<String>,
+}
+
 impl Database {
     pub fn new(dbpath: &str) -> Database {
         return Database{path: String::from(dbpath)};
@@ -50,7 +293,24 @@ impl Database {
         return Err(format!("UID not found in: {}", dn));
     }
 
-    pub fn search_user(&self, search: &str, scope: LdapSearchScope) -> Result<Vec<UserDef>, String> {
+    fn extract_cn(&self, dn: &str) -> Result<String, String> {
+        let re = match Regex::new(r"^cn=(\w+),.*?$") {
+            Ok(r) => r,
+            Err(e) => {
+                return Err(format!("Unable to create regex: {}", e).to_string());
+            }
+        };
+        for cap in re.captures_iter(dn) {
+            let cn = match cap.get(1) {
+                Some(c) => c.as_str(),
+                None => continue,
+            };
+            return Ok(cn.to_string());
+        };
+        return Err(format!("CN not found in: {}", dn));
+    }
+
+    pub fn search_user(&self, search: &str, scope: LdapSearchScope, filter_sql: &str, filter_binds: &[Value]) -> Result<Vec<UserDef>, String> {
         let conn = match self.open() {
             Ok(c) => c,
             Err(e) => {
@@ -62,13 +322,15 @@ impl Database {
         } else {
             "="
         };
-        let mut cursor = match conn.prepare(format!("select * from users where userbase{}?", filter)) {
+        let mut cursor = match conn.prepare(format!("select * from users where userbase{}? and ({})", filter, filter_sql)) {
             Ok(s) => s.into_cursor(),
             Err(e) => {
                 return Err(format!("Unable to create prepare statement: {}", e));
             },
         };
-        match cursor.bind(&[Value::String(search.to_string())]) {
+        let mut binds: Vec<Value> = vec![Value::String(search.to_string())];
+        binds.extend(filter_binds.iter().cloned());
+        match cursor.bind(binds.as_slice()) {
             Ok(c) => c,
             Err(e) => {
                 return Err(format!("Unable to fill prepared statement with data: {}", e));
@@ -97,6 +359,114 @@ impl Database {
         return Ok(res)
     }
 
+    pub fn search_groups(&self, search: &str, scope: LdapSearchScope, filter_sql: &str, filter_binds: &[Value]) -> Result<Vec<GroupDef>, String> {
+        let conn = match self.open() {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("{}", e));
+            },
+        };
+        let filter = if scope == LdapSearchScope::Subtree {
+            " like "
+        } else {
+            "="
+        };
+        let mut cursor = match conn.prepare(format!("select * from groups where groupbase{}? and ({})", filter, filter_sql)) {
+            Ok(s) => s.into_cursor(),
+            Err(e) => {
+                return Err(format!("Unable to create prepare statement: {}", e));
+            },
+        };
+        let mut binds: Vec<Value> = vec![Value::String(search.to_string())];
+        binds.extend(filter_binds.iter().cloned());
+        match cursor.bind(binds.as_slice()) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Unable to fill prepared statement with data: {}", e));
+            },
+        };
+        let mut res : Vec<GroupDef> = vec![];
+        while let Some(ln) = cursor.next().unwrap() {
+            let dn = ln[0].as_string().unwrap().to_string();
+            let cn = match self.extract_cn(dn.as_str()) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    continue;
+                }
+            };
+            let members = match self.group_members(dn.as_str()) {
+                Ok(m) => m,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    vec![]
+                },
+            };
+            res.push(GroupDef {
+                dn: dn,
+                cn: cn,
+                gid_number: ln[1].as_integer().unwrap(),
+                members: members,
+            });
+        }
+
+        return Ok(res)
+    }
+
+    fn group_members(&self, group_dn: &str) -> Result<Vec<String>, String> {
+        let conn = match self.open() {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("{}", e));
+            },
+        };
+        let mut cursor = match conn.prepare("select userbase from group_members where groupbase=?") {
+            Ok(s) => s.into_cursor(),
+            Err(e) => {
+                return Err(format!("Unable to create prepare statement: {}", e));
+            },
+        };
+        match cursor.bind(&[Value::String(group_dn.to_string())]) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Unable to fill prepared statement with data: {}", e));
+            },
+        };
+        let mut res : Vec<String> = vec![];
+        while let Some(ln) = cursor.next().unwrap() {
+            res.push(ln[0].as_string().unwrap().to_string());
+        }
+
+        return Ok(res)
+    }
+
+    pub fn member_of(&self, user_dn: &str) -> Result<Vec<String>, String> {
+        let conn = match self.open() {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("{}", e));
+            },
+        };
+        let mut cursor = match conn.prepare("select groupbase from group_members where userbase=?") {
+            Ok(s) => s.into_cursor(),
+            Err(e) => {
+                return Err(format!("Unable to create prepare statement: {}", e));
+            },
+        };
+        match cursor.bind(&[Value::String(user_dn.to_string())]) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Unable to fill prepared statement with data: {}", e));
+            },
+        };
+        let mut res : Vec<String> = vec![];
+        while let Some(ln) = cursor.next().unwrap() {
+            res.push(ln[0].as_string().unwrap().to_string());
+        }
+
+        return Ok(res)
+    }
+
     fn open(&self) -> Result<Connection, String> {
         let of = OpenFlags::new().set_read_only();
         match sqlite::Connection::open_with_flags(self.path.clone(), of) {
@@ -108,14 +478,58 @@ impl Database {
             },
         };
     }
+
+    fn open_rw(&self) -> Result<Connection, String> {
+        match sqlite::Connection::open(self.path.clone()) {
+            Ok(c) => {
+                return Ok(c);
+            },
+            Err(e) => {
+                return Err(format!("Unable to open filepath: {}", e));
+            },
+        };
+    }
+
+    pub fn update_password(&self, dn: &str, new_passhash: &str) -> Result<(), String> {
+        let conn = match self.open_rw() {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("{}", e));
+            },
+        };
+        let mut cursor = match conn.prepare("update users set passhash=? where userbase=?") {
+            Ok(s) => s.into_cursor(),
+            Err(e) => {
+                return Err(format!("Unable to create prepare statement: {}", e));
+            },
+        };
+        match cursor.bind(&[Value::String(new_passhash.to_string()), Value::String(dn.to_string())]) {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Unable to fill prepared statement with data: {}", e));
+            },
+        };
+        match cursor.next() {
+            Some(Ok(_)) | None => {
+                return Ok(());
+            },
+            Some(Err(e)) => {
+                return Err(format!("Unable to execute update: {}", e));
+            },
+        };
+    }
 }
 
 struct LdapSession {
     dn: String,
     maysearch: bool,
+    base_dn: String,
 }
 
 impl LdapSession {
+    // Checks both that `dn` is well-formed and that it actually falls under
+    // this server's configured base DN, so a client can't bind or search
+    // against an arbitrary, unrelated tree.
     fn check_dn_format(&self, dn: &str) -> Result<bool, String> {
         let re = match Regex::new(r"^(?:\w+=\w+,)*?(?:\w+=\w+)$") {
             Ok(r) => r,
@@ -128,6 +542,10 @@ impl LdapSession {
             eprintln!("Non-conformant bind string: {}", dn);
             return Ok(false);
         }
+        if dn != self.base_dn && !dn.ends_with(format!(",{}", self.base_dn).as_str()) {
+            eprintln!("DN outside of configured base {}: {}", self.base_dn, dn);
+            return Ok(false);
+        }
         return Ok(true);
     }
 
@@ -144,19 +562,15 @@ impl LdapSession {
                 return sbr.gen_error(LdapResultCode::Other, e);
             }
         };
-        let users = match db.search_user(sbr.dn.as_str(), LdapSearchScope::Base) {
+        let users = match db.search_user(sbr.dn.as_str(), LdapSearchScope::Base, "1=1", &[]) {
             Ok(u) => u,
             Err(e) => {
                 eprintln!("{}", e);
                 return sbr.gen_error(LdapResultCode::OperationsError, e);
             }
         };
-        let mut hasher = Sha512::new();
-        hasher.input_str(sbr.pw.as_str());
-        let passhex = hasher.result_str();
-        println!("    Bind pass hex: {}", passhex);
         for user in users {
-            if user.passhash == passhex {
+            if verify_password(user.passhash.as_str(), sbr.pw.as_str()) {
                 self.dn = user.dn;
                 if user.maysearch > 0 {
                     self.maysearch = true;
@@ -169,32 +583,190 @@ impl LdapSession {
         return sbr.gen_invalid_cred();
     }
 
-    fn recurse_filters(&self, fltr: &ldap3_server::LdapFilter) -> String {
+    // Resolves a single requested attribute name onto the canonical LDAP
+    // attribute name and value vector to emit for it, accepting the
+    // `sn`/`surname` and `mail`/`email` aliases. Returns Err for anything
+    // this server doesn't know how to serve so the caller can skip it.
+    fn get_attribute(user: &UserDef, name: &str) -> Result<(String, Vec<String>), ()> {
+        match name.to_lowercase().as_str() {
+            "objectclass" => Ok(("objectClass".to_string(), vec![
+                "inetOrgPerson".to_string(), "posixAccount".to_string(), "mailAccount".to_string(),
+            ])),
+            "cn" => Ok(("cn".to_string(), vec![format!("{} {}", user.given_name, user.surname)])),
+            "uid" => Ok(("uid".to_string(), vec![user.uid.clone()])),
+            "givenname" => Ok(("givenName".to_string(), vec![user.given_name.clone()])),
+            "surname" | "sn" => Ok(("sn".to_string(), vec![user.surname.clone()])),
+            "mail" | "email" => Ok(("mail".to_string(), vec![user.email.clone()])),
+            _ => Err(()),
+        }
+    }
+
+    // Maps an LDAP attribute name onto the SQL column (or expression) that
+    // backs it. `uid` isn't a column of its own -- it's folded into
+    // `userbase` -- so it's handled separately by the callers below.
+    fn column_for_attr(attr: &str) -> Option<&'static str> {
+        match attr.to_lowercase().as_str() {
+            "mail" | "email" => Some("email"),
+            "givenname" => Some("givenname"),
+            "surname" | "sn" => Some("surname"),
+            "cn" => Some("(givenname || ' ' || surname)"),
+            _ => None,
+        }
+    }
+
+    // Walks an `LdapFilter` tree and produces a parameterized SQL WHERE
+    // fragment together with the values to bind to it. Attributes this
+    // server doesn't know about translate to a constant-false predicate
+    // rather than silently matching everything.
+    fn translate_filter(&self, fltr: &LdapFilter) -> (String, Vec<Value>) {
         match fltr {
-            LdapFilter::Equality(itm, uname) => {
-                if itm == "uid" {
-                    return uname.to_string();
+            LdapFilter::Equality(attr, val) => {
+                if attr.eq_ignore_ascii_case("uid") {
+                    return ("userbase LIKE ?".to_string(), vec![Value::String(format!("uid={},%", val))]);
+                }
+                if attr.eq_ignore_ascii_case("objectclass") {
+                    let matches = ["inetorgperson", "posixaccount", "mailaccount"]
+                        .contains(&val.to_lowercase().as_str());
+                    return (if matches { "1=1".to_string() } else { "0=1".to_string() }, vec![]);
+                }
+                match Self::column_for_attr(attr) {
+                    Some(col) => (format!("{} = ?", col), vec![Value::String(val.clone())]),
+                    None => ("0=1".to_string(), vec![]),
                 }
             },
-            LdapFilter::And(fltrs) => {
-                for fltr in fltrs {
-                    let res = self.recurse_filters(fltr);
-                    if res != "%" {
-                        return res;
-                    }
+            LdapFilter::Present(attr) => {
+                if attr.eq_ignore_ascii_case("uid") {
+                    return ("userbase LIKE 'uid=%'".to_string(), vec![]);
+                }
+                if attr.eq_ignore_ascii_case("objectclass") {
+                    return ("1=1".to_string(), vec![]);
+                }
+                match Self::column_for_attr(attr) {
+                    Some(col) => (format!("{} IS NOT NULL", col), vec![]),
+                    None => ("0=1".to_string(), vec![]),
                 }
             },
-            LdapFilter::Or(fltrs) => {
-                for fltr in fltrs {
-                    let res = self.recurse_filters(fltr);
-                    if res != "%" {
-                        return res;
-                    }
+            LdapFilter::Substring(attr, val) => {
+                let pattern = val.replace('*', "%");
+                if attr.eq_ignore_ascii_case("uid") {
+                    return ("userbase LIKE ?".to_string(), vec![Value::String(format!("uid={},%", pattern))]);
+                }
+                match Self::column_for_attr(attr) {
+                    Some(col) => (format!("{} LIKE ?", col), vec![Value::String(pattern)]),
+                    None => ("0=1".to_string(), vec![]),
                 }
             },
-            _ => {},
-        };
-        return "%".to_string();
+            LdapFilter::And(fltrs) => self.translate_junction(fltrs, "AND"),
+            LdapFilter::Or(fltrs) => self.translate_junction(fltrs, "OR"),
+            LdapFilter::Not(inner) => {
+                let (frag, binds) = self.translate_filter(inner);
+                (format!("NOT ({})", frag), binds)
+            },
+            _ => ("0=1".to_string(), vec![]),
+        }
+    }
+
+    fn translate_junction(&self, fltrs: &[LdapFilter], op: &str) -> (String, Vec<Value>) {
+        if fltrs.is_empty() {
+            return (if op == "AND" { "1=1".to_string() } else { "0=1".to_string() }, vec![]);
+        }
+        let mut frags : Vec<String> = vec![];
+        let mut binds : Vec<Value> = vec![];
+        for fltr in fltrs {
+            let (frag, mut fbinds) = self.translate_filter(fltr);
+            frags.push(format!("({})", frag));
+            binds.append(&mut fbinds);
+        }
+        (frags.join(format!(" {} ", op).as_str()), binds)
+    }
+
+    // Same idea as column_for_attr/translate_filter, but for the groups
+    // subtree: `cn` is folded into `groupbase` (like `uid` is for users),
+    // `gidNumber` is a real column, and `member` isn't filterable since
+    // membership lives in the separate group_members table.
+    fn column_for_group_attr(attr: &str) -> Option<&'static str> {
+        match attr.to_lowercase().as_str() {
+            "gidnumber" => Some("gidnumber"),
+            _ => None,
+        }
+    }
+
+    fn translate_group_filter(&self, fltr: &LdapFilter) -> (String, Vec<Value>) {
+        match fltr {
+            LdapFilter::Equality(attr, val) => {
+                if attr.eq_ignore_ascii_case("cn") {
+                    return ("groupbase LIKE ?".to_string(), vec![Value::String(format!("cn={},%", val))]);
+                }
+                if attr.eq_ignore_ascii_case("objectclass") {
+                    let matches = ["posixgroup", "groupofnames"].contains(&val.to_lowercase().as_str());
+                    return (if matches { "1=1".to_string() } else { "0=1".to_string() }, vec![]);
+                }
+                match Self::column_for_group_attr(attr) {
+                    Some(col) => (format!("{} = ?", col), vec![Value::String(val.clone())]),
+                    None => ("0=1".to_string(), vec![]),
+                }
+            },
+            LdapFilter::Present(attr) => {
+                if attr.eq_ignore_ascii_case("cn") {
+                    return ("groupbase LIKE 'cn=%'".to_string(), vec![]);
+                }
+                if attr.eq_ignore_ascii_case("objectclass") {
+                    return ("1=1".to_string(), vec![]);
+                }
+                match Self::column_for_group_attr(attr) {
+                    Some(col) => (format!("{} IS NOT NULL", col), vec![]),
+                    None => ("0=1".to_string(), vec![]),
+                }
+            },
+            LdapFilter::Substring(attr, val) => {
+                let pattern = val.replace('*', "%");
+                if attr.eq_ignore_ascii_case("cn") {
+                    return ("groupbase LIKE ?".to_string(), vec![Value::String(format!("cn={},%", pattern))]);
+                }
+                match Self::column_for_group_attr(attr) {
+                    Some(col) => (format!("{} LIKE ?", col), vec![Value::String(pattern)]),
+                    None => ("0=1".to_string(), vec![]),
+                }
+            },
+            LdapFilter::And(fltrs) => self.translate_group_junction(fltrs, "AND"),
+            LdapFilter::Or(fltrs) => self.translate_group_junction(fltrs, "OR"),
+            LdapFilter::Not(inner) => {
+                let (frag, binds) = self.translate_group_filter(inner);
+                (format!("NOT ({})", frag), binds)
+            },
+            _ => ("0=1".to_string(), vec![]),
+        }
+    }
+
+    fn translate_group_junction(&self, fltrs: &[LdapFilter], op: &str) -> (String, Vec<Value>) {
+        if fltrs.is_empty() {
+            return (if op == "AND" { "1=1".to_string() } else { "0=1".to_string() }, vec![]);
+        }
+        let mut frags : Vec<String> = vec![];
+        let mut binds : Vec<Value> = vec![];
+        for fltr in fltrs {
+            let (frag, mut fbinds) = self.translate_group_filter(fltr);
+            frags.push(format!("({})", frag));
+            binds.append(&mut fbinds);
+        }
+        (frags.join(format!(" {} ", op).as_str()), binds)
+    }
+
+    // Recursively checks whether a filter identifies group entries (an
+    // `objectClass` equality test for `posixGroup`/`groupOfNames`,
+    // or anywhere under an And/Or/Not of one), so a group-identifying
+    // filter routes to the groups subtree even when the search base itself
+    // doesn't say `ou=groups`.
+    fn filter_targets_groups(fltr: &LdapFilter) -> bool {
+        match fltr {
+            LdapFilter::Equality(attr, val) => {
+                attr.eq_ignore_ascii_case("objectclass")
+                    && ["posixgroup", "groupofnames"].contains(&val.to_lowercase().as_str())
+            },
+            LdapFilter::And(fltrs) | LdapFilter::Or(fltrs) => fltrs.iter().any(Self::filter_targets_groups),
+            LdapFilter::Not(inner) => Self::filter_targets_groups(inner),
+            _ => false,
+        }
     }
 
     pub fn do_search(&mut self, lsr: &SearchRequest, db: Box<Database>) -> Vec<LdapMsg> {
@@ -213,6 +785,9 @@ impl LdapSession {
                 return vec![lsr.gen_error(LdapResultCode::Other, e)];
             }
         };
+        if lsr.base.to_lowercase().contains("ou=groups") || Self::filter_targets_groups(&lsr.filter) {
+            return self.do_search_groups(lsr, db);
+        }
         let re = match Regex::new(r"^uid=\w+,(.*?)$") {
             Ok(r) => r,
             Err(e) => {
@@ -226,16 +801,13 @@ impl LdapSession {
             search = lsr.base.to_string();
             perform_tuname_check = false;
         } else {
-            let tuname = self.recurse_filters(&lsr.filter);
-            search = format!("uid={},{}", tuname, lsr.base);
-            if tuname == "%" {
-                perform_tuname_check = true;
-            } else {
-                perform_tuname_check = false;
-            }
+            search = format!("uid=%,{}", lsr.base);
+            perform_tuname_check = true;
         }
         println!("    User search: {}", search);
-        let users = match db.search_user(search.as_str(), lsr.scope.clone()) {
+        let (filter_sql, filter_binds) = self.translate_filter(&lsr.filter);
+        println!("    Filter SQL: {} {:?}", filter_sql, filter_binds);
+        let users = match db.search_user(search.as_str(), lsr.scope.clone(), filter_sql.as_str(), filter_binds.as_slice()) {
             Ok(u) => u,
             Err(e) => {
                 eprintln!("{}", e);
@@ -260,32 +832,112 @@ impl LdapSession {
                 continue;
             }
             println!("    User found: {}", user.dn);
+            let attr_names: Vec<String> = if lsr.attrs.is_empty() || lsr.attrs.iter().any(|a| a == "*") {
+                vec!["objectClass", "cn", "uid", "givenName", "sn", "mail", "memberOf"]
+                    .into_iter().map(|s| s.to_string()).collect()
+            } else {
+                lsr.attrs.clone()
+            };
+            let mut attributes: Vec<LdapPartialAttribute> = vec![];
+            for name in &attr_names {
+                if name.eq_ignore_ascii_case("memberof") {
+                    let member_of = match db.member_of(user.dn.as_str()) {
+                        Ok(m) => m,
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            vec![]
+                        },
+                    };
+                    if !member_of.is_empty() {
+                        attributes.push(LdapPartialAttribute {
+                            atype: "memberOf".to_string(),
+                            vals: member_of,
+                        });
+                    }
+                    continue;
+                }
+                match Self::get_attribute(&user, name) {
+                    Ok((atype, vals)) => attributes.push(LdapPartialAttribute { atype, vals }),
+                    Err(_) => continue,
+                }
+            }
             res.push(lsr.gen_result_entry(LdapSearchResultEntry {
                         dn: user.dn,
+                        attributes: attributes,
+                    }));
+        }
+        res.push(lsr.gen_success());
+        return res;
+    }
+
+    fn do_search_groups(&mut self, lsr: &SearchRequest, db: Box<Database>) -> Vec<LdapMsg> {
+        println!("    Group search under: {}", lsr.base);
+        let re = match Regex::new(r"^cn=\w+,(.*?)$") {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Unable to create regex: {}", e);
+                return vec![lsr.gen_error(LdapResultCode::Other, "Internal Server Error".to_string())];
+            }
+        };
+        let search : String;
+        let perform_cn_check : bool;
+        if re.is_match(lsr.base.as_str()) {
+            search = lsr.base.to_string();
+            perform_cn_check = false;
+        } else {
+            search = format!("cn=%,{}", lsr.base);
+            perform_cn_check = true;
+        }
+        println!("    Group search: {}", search);
+        let (filter_sql, filter_binds) = self.translate_group_filter(&lsr.filter);
+        let groups = match db.search_groups(search.as_str(), lsr.scope.clone(), filter_sql.as_str(), filter_binds.as_slice()) {
+            Ok(g) => g,
+            Err(e) => {
+                eprintln!("{}", e);
+                return vec![lsr.gen_error(LdapResultCode::Other, "Internal Server Error".to_string())];
+            }
+        };
+        let mut res : Vec<LdapMsg> = vec![];
+        // Allows any number of intervening RDNs (e.g. `ou=groups,`) between
+        // the matched `cn=...` and the search base, so a true subtree
+        // search still finds groups that aren't a direct child of `base` --
+        // which matters now that a group-identifying filter alone (not just
+        // an `ou=groups` base) can route a search here.
+        let cn_check = match Regex::new(format!(r"cn=\w+,(?:\w+=\w+,)*{}", lsr.base).as_str()) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Unable to create cn_check regex: {}", e);
+                return vec![lsr.gen_error(LdapResultCode::Other, "Internal Server Error".to_string())];
+            }
+        };
+        for group in groups {
+            if perform_cn_check {
+                if !cn_check.is_match(group.dn.as_str()) {
+                    continue;
+                }
+            }
+            if !self.maysearch {
+                continue;
+            }
+            println!("    Group found: {}", group.dn);
+            res.push(lsr.gen_result_entry(LdapSearchResultEntry {
+                        dn: group.dn,
                         attributes: vec![
                             LdapPartialAttribute {
                                 atype: "objectClass".to_string(),
-                                vals: vec!["users".to_string()],
+                                vals: vec!["groupOfNames".to_string(), "posixGroup".to_string()],
                             },
                             LdapPartialAttribute {
                                 atype: "cn".to_string(),
-                                vals: vec![format!("{} {}", user.given_name, user.surname)],
-                            },
-                            LdapPartialAttribute {
-                                atype: "uid".to_string(),
-                                vals: vec![user.uid],
+                                vals: vec![group.cn],
                             },
                             LdapPartialAttribute {
-                                atype: "givenName".to_string(),
-                                vals: vec![user.given_name],
+                                atype: "gidNumber".to_string(),
+                                vals: vec![group.gid_number.to_string()],
                             },
                             LdapPartialAttribute {
-                                atype: "surname".to_string(),
-                                vals: vec![user.surname],
-                            },
-                            LdapPartialAttribute {
-                                atype: "email".to_string(),
-                                vals: vec![user.email],
+                                atype: "member".to_string(),
+                                vals: group.members,
                             },
                         ],
                     }));
@@ -297,9 +949,79 @@ impl LdapSession {
     pub fn do_whoami(&mut self, wr: &WhoamiRequest) -> LdapMsg {
         wr.gen_success(format!("dn: {}", self.dn).as_str())
     }
+
+    pub fn do_password_modify(&mut self, msgid: i32, ext: &LdapExtendedRequest, db: Box<Database>) -> LdapMsg {
+        println!("Performing password modify:
+    DN: {}", self.dn);
+        let pwmod = match LdapPasswordModifyRequest::try_from(ext) {
+            Ok(p) => p,
+            Err(e) => {
+                eprintln!("Unable to decode password modify request: {:?}", e);
+                return gen_extended_response(msgid, LdapResultCode::ProtocolError, "Malformed password modify request".to_string());
+            }
+        };
+        let target_dn = match pwmod.user_identity.clone().or_else(|| {
+            if self.dn != "Anonymous" {
+                Some(self.dn.clone())
+            } else {
+                None
+            }
+        }) {
+            Some(dn) => dn,
+            None => {
+                return gen_extended_response(msgid, LdapResultCode::InsufficentAccessRights, "No identity to modify the password for".to_string());
+            }
+        };
+        let old_passwd = match pwmod.old_password {
+            Some(p) => p,
+            None => {
+                return gen_extended_response(msgid, LdapResultCode::UnwillingToPerform, "oldPasswd is required".to_string());
+            }
+        };
+        let new_passwd = match pwmod.new_password {
+            Some(p) => p,
+            None => {
+                return gen_extended_response(msgid, LdapResultCode::UnwillingToPerform, "newPasswd is required".to_string());
+            }
+        };
+        let users = match db.search_user(target_dn.as_str(), LdapSearchScope::Base, "1=1", &[]) {
+            Ok(u) => u,
+            Err(e) => {
+                eprintln!("{}", e);
+                return gen_extended_response(msgid, LdapResultCode::OperationsError, e);
+            }
+        };
+        let user = match users.into_iter().find(|u| u.dn == target_dn) {
+            Some(u) => u,
+            None => {
+                return gen_extended_response(msgid, LdapResultCode::NoSuchObject, format!("No such user: {}", target_dn));
+            }
+        };
+        if !verify_password(user.passhash.as_str(), old_passwd.as_str()) {
+            println!("    Password modify failed: old password mismatch");
+            return gen_extended_response(msgid, LdapResultCode::InvalidCredentials, "oldPasswd does not match".to_string());
+        }
+        let new_hash = hash_password(new_passwd.as_str());
+        match db.update_password(target_dn.as_str(), new_hash.as_str()) {
+            Ok(_) => {
+                println!("    Password modify success: {}", target_dn);
+                return gen_extended_response(msgid, LdapResultCode::Success, "".to_string());
+            },
+            Err(e) => {
+                eprintln!("{}", e);
+                return gen_extended_response(msgid, LdapResultCode::OperationsError, e);
+            }
+        };
+    }
 }
 
-async fn handle_client(socket: TcpStream, _paddr: net::SocketAddr, db: Box<Database>) {
+// Generic over the stream type so the same client loop serves both the
+// plaintext listener (`TcpStream`) and the LDAPS listener, where the
+// stream has already been wrapped by the `TlsAcceptor`.
+async fn handle_client<S>(socket: S, _paddr: net::SocketAddr, db: Box<Database>, base_dn: String)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
     // Configure the codec etc.
     let (r, w) = tokio::io::split(socket);
     let mut reqs = FramedRead::new(r, LdapCodec);
@@ -308,11 +1030,12 @@ async fn handle_client(socket: TcpStream, _paddr: net::SocketAddr, db: Box<Datab
     let mut session = LdapSession {
         dn: "Anonymous".to_string(),
         maysearch: false,
+        base_dn: base_dn,
     };
 
     while let Some(msg) = reqs.next().await {
-        let server_op = match msg.map_err(|_e| ()).and_then(|msg| ServerOps::try_from(msg)) {
-            Ok(v) => v,
+        let msg = match msg {
+            Ok(m) => m,
             Err(_) => {
                 let _err = resp
                     .send(DisconnectionNotice::gen(LdapResultCode::Other,
@@ -323,14 +1046,43 @@ async fn handle_client(socket: TcpStream, _paddr: net::SocketAddr, db: Box<Datab
             }
         };
 
-        let result = match server_op {
-            ServerOps::SimpleBind(sbr) => vec![session.do_bind(&sbr, db.clone())],
-            ServerOps::Search(sr) => session.do_search(&sr, db.clone()),
-            ServerOps::Unbind(_) => {
-                // No need to notify on unbind (per rfc4511)
-                return;
+        // Extended requests other than Who-am-I (e.g. Password Modify) aren't
+        // representable by the simple::ServerOps enum, so they're dispatched
+        // straight off the underlying LdapOp before falling back to ServerOps
+        // for everything else, Who-am-I included.
+        let ext_req = match msg.op {
+            LdapOp::ExtendedRequest(ref ext) if ext.name != OID_WHOAMI => Some(ext),
+            _ => None,
+        };
+        let result = if let Some(ext) = ext_req {
+            if ext.name == OID_PASSWORD_MODIFY {
+                vec![session.do_password_modify(msg.msgid, ext, db.clone())]
+            } else {
+                vec![gen_extended_response(msg.msgid, LdapResultCode::ProtocolError,
+                    format!("Unsupported extended operation: {}", ext.name))]
+            }
+        } else {
+            let server_op = match ServerOps::try_from(msg) {
+                Ok(v) => v,
+                Err(_) => {
+                    let _err = resp
+                        .send(DisconnectionNotice::gen(LdapResultCode::Other,
+                            "Internal Server Error",
+                        )).await;
+                    let _err = resp.flush().await;
+                    return;
+                }
+            };
+
+            match server_op {
+                ServerOps::SimpleBind(sbr) => vec![session.do_bind(&sbr, db.clone())],
+                ServerOps::Search(sr) => session.do_search(&sr, db.clone()),
+                ServerOps::Unbind(_) => {
+                    // No need to notify on unbind (per rfc4511)
+                    return;
+                }
+                ServerOps::Whoami(wr) => vec![session.do_whoami(&wr)],
             }
-            ServerOps::Whoami(wr) => vec![session.do_whoami(&wr)],
         };
 
         for rmsg in result.into_iter() {
@@ -346,11 +1098,36 @@ async fn handle_client(socket: TcpStream, _paddr: net::SocketAddr, db: Box<Datab
     // Client disconnected
 }
 
-async fn acceptor(listener: Box<TcpListener>, db: Box<Database>) {
+async fn acceptor(listener: Box<TcpListener>, db: Box<Database>, base_dn: String) {
+    loop {
+        match listener.accept().await {
+            Ok((socket, paddr)) => {
+                tokio::spawn(handle_client(socket, paddr, db.clone(), base_dn.clone()));
+            }
+            Err(e) => {
+                eprintln!("Unable to accept client: {}", e);
+            },
+        };
+    }
+}
+
+// Same accept loop as `acceptor`, but wraps each socket in the `TlsAcceptor`
+// before handing it to `handle_client`, for the LDAPS listener.
+async fn acceptor_tls(listener: Box<TcpListener>, db: Box<Database>, base_dn: String, tls_acceptor: TlsAcceptor) {
     loop {
         match listener.accept().await {
             Ok((socket, paddr)) => {
-                tokio::spawn(handle_client(socket, paddr, db.clone()));
+                let tls_acceptor = tls_acceptor.clone();
+                let db = db.clone();
+                let base_dn = base_dn.clone();
+                tokio::spawn(async move {
+                    match tls_acceptor.accept(socket).await {
+                        Ok(tls_socket) => handle_client(tls_socket, paddr, db, base_dn).await,
+                        Err(e) => {
+                            eprintln!("TLS handshake failed: {}", e);
+                        }
+                    };
+                });
             }
             Err(e) => {
                 eprintln!("Unable to accept client: {}", e);
@@ -361,11 +1138,11 @@ async fn acceptor(listener: Box<TcpListener>, db: Box<Database>) {
 
 #[tokio::main]
 async fn main() -> () {
-    let addr = "0.0.0.0:12345";
+    let config = Config::load();
 
-    let db = Box::new(Database::new("database.sqlite"));
+    let db = Box::new(Database::new(config.db_path.as_str()));
 
-    let addr = match net::SocketAddr::from_str(addr) {
+    let addr = match net::SocketAddr::from_str(config.listen_addr.as_str()) {
         Ok(a) => a,
         Err(e) => {
             eprintln!("Unable to build address: {}", e);
@@ -382,9 +1159,39 @@ async fn main() -> () {
     let listener = Box::new(listener);
 
     // Initiate the acceptor task.
-    tokio::spawn(acceptor(listener, db));
+    tokio::spawn(acceptor(listener, db.clone(), config.base_dn.clone()));
 
     println!("started ldap://{} ...", addr);
+
+    if config.tls_enabled() {
+        let tls_acceptor = match load_tls_acceptor(config.tls_cert_path.as_str(), config.tls_key_path.as_str()) {
+            Ok(a) => a,
+            Err(e) => {
+                eprintln!("Unable to set up TLS: {}", e);
+                process::exit(-1);
+            }
+        };
+        let ldaps_addr = match net::SocketAddr::from_str(config.ldaps_listen_addr.as_str()) {
+            Ok(a) => a,
+            Err(e) => {
+                eprintln!("Unable to build LDAPS address: {}", e);
+                process::exit(-1);
+            },
+        };
+        let ldaps_listener = match TcpListener::bind(&ldaps_addr).await {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("Unable to bind to LDAPS address: {}", e);
+                process::exit(-1);
+            },
+        };
+        let ldaps_listener = Box::new(ldaps_listener);
+
+        tokio::spawn(acceptor_tls(ldaps_listener, db, config.base_dn.clone(), tls_acceptor));
+
+        println!("started ldaps://{} ...", ldaps_addr);
+    }
+
     match tokio::signal::ctrl_c().await {
         Ok(_) => {},
         Err(e) => {
@@ -392,4 +1199,110 @@ async fn main() -> () {
             process::exit(-1);
         },
     };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session() -> LdapSession {
+        LdapSession {
+            dn: "Anonymous".to_string(),
+            maysearch: false,
+            base_dn: "dc=example,dc=com".to_string(),
+        }
+    }
+
+    #[test]
+    fn translate_filter_maps_known_attr() {
+        let (sql, binds) = session().translate_filter(&LdapFilter::Equality("mail".to_string(), "alice@example.com".to_string()));
+        assert_eq!(sql, "email = ?");
+        assert_eq!(binds, vec![Value::String("alice@example.com".to_string())]);
+    }
+
+    #[test]
+    fn translate_filter_unknown_attr_is_always_false() {
+        let (sql, binds) = session().translate_filter(&LdapFilter::Equality("unknownAttr".to_string(), "x".to_string()));
+        assert_eq!(sql, "0=1");
+        assert!(binds.is_empty());
+    }
+
+    #[test]
+    fn translate_filter_objectclass_equality_matches_served_classes() {
+        let (served, _) = session().translate_filter(&LdapFilter::Equality("objectClass".to_string(), "posixAccount".to_string()));
+        assert_eq!(served, "1=1");
+        let (unserved, _) = session().translate_filter(&LdapFilter::Equality("objectClass".to_string(), "posixGroup".to_string()));
+        assert_eq!(unserved, "0=1");
+    }
+
+    #[test]
+    fn translate_filter_objectclass_present_is_always_true() {
+        let (sql, _) = session().translate_filter(&LdapFilter::Present("objectClass".to_string()));
+        assert_eq!(sql, "1=1");
+    }
+
+    #[test]
+    fn translate_filter_and_or_not() {
+        let (and_sql, and_binds) = session().translate_filter(&LdapFilter::And(vec![
+            LdapFilter::Equality("uid".to_string(), "alice".to_string()),
+            LdapFilter::Equality("mail".to_string(), "a@b.com".to_string()),
+        ]));
+        assert_eq!(and_sql, "(userbase LIKE ?) AND (email = ?)");
+        assert_eq!(and_binds.len(), 2);
+
+        let (not_sql, _) = session().translate_filter(&LdapFilter::Not(Box::new(LdapFilter::Present("uid".to_string()))));
+        assert_eq!(not_sql, "NOT (userbase LIKE 'uid=%')");
+    }
+
+    #[test]
+    fn translate_filter_empty_junction() {
+        let (and_sql, _) = session().translate_filter(&LdapFilter::And(vec![]));
+        assert_eq!(and_sql, "1=1");
+        let (or_sql, _) = session().translate_filter(&LdapFilter::Or(vec![]));
+        assert_eq!(or_sql, "0=1");
+    }
+
+    #[test]
+    fn translate_group_filter_objectclass() {
+        let (sql, _) = session().translate_group_filter(&LdapFilter::Equality("objectClass".to_string(), "groupOfNames".to_string()));
+        assert_eq!(sql, "1=1");
+        let (unserved, _) = session().translate_group_filter(&LdapFilter::Equality("objectClass".to_string(), "inetOrgPerson".to_string()));
+        assert_eq!(unserved, "0=1");
+    }
+
+    #[test]
+    fn filter_targets_groups_detects_nested_objectclass() {
+        let fltr = LdapFilter::And(vec![LdapFilter::Equality("objectClass".to_string(), "posixGroup".to_string())]);
+        assert!(LdapSession::filter_targets_groups(&fltr));
+        assert!(!LdapSession::filter_targets_groups(&LdapFilter::Present("uid".to_string())));
+    }
+
+    #[test]
+    fn password_roundtrip_ssha512() {
+        let hash = hash_password("hunter2");
+        assert!(hash.starts_with(SCHEME_SSHA512));
+        assert!(verify_password(hash.as_str(), "hunter2"));
+        assert!(!verify_password(hash.as_str(), "wrong"));
+    }
+
+    #[test]
+    fn password_verify_bare_sha512_is_legacy_unprefixed() {
+        let legacy = sha512_hex(b"hunter2");
+        assert!(verify_password(legacy.as_str(), "hunter2"));
+        assert!(!verify_password(legacy.as_str(), "wrong"));
+    }
+
+    #[test]
+    fn password_verify_prefixed_sha512() {
+        let hash = format!("{}{}", SCHEME_SHA512, sha512_hex(b"hunter2"));
+        assert!(verify_password(hash.as_str(), "hunter2"));
+    }
+
+    #[test]
+    fn password_verify_argon2() {
+        let encoded = argon2::hash_encoded(b"hunter2", b"saltysalt", &argon2::Config::default()).unwrap();
+        let hash = format!("{}{}", SCHEME_ARGON2, encoded);
+        assert!(verify_password(hash.as_str(), "hunter2"));
+        assert!(!verify_password(hash.as_str(), "wrong"));
+    }
 }
\ No newline at end of file